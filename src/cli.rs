@@ -0,0 +1,253 @@
+use anyhow::{bail, Context, Result};
+use csv::{ReaderBuilder, Terminator, Trim, WriterBuilder};
+use glob::Pattern;
+
+/// How the canonical column set for a directory is determined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderMode {
+    /// Canonical columns are exactly the header of the first (sorted) file.
+    First,
+    /// Canonical columns are the ordered union of every file's header in the directory.
+    Union,
+}
+
+impl HeaderMode {
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "first" => Ok(HeaderMode::First),
+            "union" => Ok(HeaderMode::Union),
+            other => bail!("unknown --header-mode '{}': expected 'first' or 'union'", other),
+        }
+    }
+}
+
+/// Parse a `--trim` value into the csv crate's `Trim` setting.
+fn parse_trim(s: &str) -> Result<Trim> {
+    match s {
+        "none" => Ok(Trim::None),
+        "headers" => Ok(Trim::Headers),
+        "fields" => Ok(Trim::Fields),
+        "all" => Ok(Trim::All),
+        other => bail!(
+            "unknown --trim '{}': expected 'none', 'headers', 'fields' or 'all'",
+            other
+        ),
+    }
+}
+
+/// Parse a `--terminator` value: `crlf`, or a single ASCII character.
+fn parse_terminator(s: &str) -> Result<Terminator> {
+    if s.eq_ignore_ascii_case("crlf") {
+        return Ok(Terminator::CRLF);
+    }
+    let ch = s
+        .chars()
+        .next()
+        .filter(|_| s.chars().count() == 1)
+        .with_context(|| format!("--terminator must be 'crlf' or a single character, got '{}'", s))?;
+    if !ch.is_ascii() {
+        bail!("--terminator must be an ASCII character");
+    }
+    Ok(Terminator::Any(ch as u8))
+}
+
+/// Split a comma-separated `--include-ext`/`--exclude-ext` value into
+/// bare, lowercase extensions (a leading '.' is tolerated and stripped).
+fn split_ext_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+/// Parse a single-ASCII-character flag value (used for `--quote`).
+fn parse_ascii_char(flag: &str, s: &str) -> Result<u8> {
+    let ch = s
+        .chars()
+        .next()
+        .filter(|_| s.chars().count() == 1)
+        .with_context(|| format!("{} must be a single character, got '{}'", flag, s))?;
+    if !ch.is_ascii() {
+        bail!("{} must be an ASCII character", flag);
+    }
+    Ok(ch as u8)
+}
+
+pub struct Config {
+    pub root_dir: String,
+    pub out_dir: String,
+    pub delim: u8,
+    pub header_mode: HeaderMode,
+    pub jobs: usize,
+    pub trim: Trim,
+    pub flexible: bool,
+    pub quote: u8,
+    pub terminator: Option<Terminator>,
+    pub dedup: bool,
+    pub dedup_key: Option<Vec<String>>,
+    pub recursive: bool,
+    pub include_ext: Option<Vec<String>>,
+    pub exclude_ext: Vec<String>,
+    pub exclude_dir: Vec<Pattern>,
+    pub compress: bool,
+    pub manifest: bool,
+}
+
+impl Config {
+    /// A `ReaderBuilder` preconfigured with the delimiter, trim, flexible,
+    /// quote and terminator settings shared by every CSV reader in the
+    /// pipeline. Without an explicit `--terminator`, this keeps the csv
+    /// crate's own reader default (`Terminator::CRLF`, which accepts `\r`,
+    /// `\n` and `\r\n`) so mixed line endings still parse correctly.
+    pub fn reader_builder(&self) -> ReaderBuilder {
+        let mut builder = ReaderBuilder::new();
+        builder
+            .has_headers(true)
+            .delimiter(self.delim)
+            .trim(self.trim)
+            .flexible(self.flexible)
+            .quote(self.quote)
+            .terminator(self.terminator.unwrap_or(Terminator::CRLF));
+        builder
+    }
+
+    /// A `WriterBuilder` preconfigured with the delimiter, quote and
+    /// terminator settings shared by every CSV writer in the pipeline.
+    /// Without an explicit `--terminator`, this keeps the csv crate's own
+    /// writer default (`\n`).
+    pub fn writer_builder(&self) -> WriterBuilder {
+        let mut builder = WriterBuilder::new();
+        builder
+            .delimiter(self.delim)
+            .quote(self.quote)
+            .terminator(self.terminator.unwrap_or(Terminator::Any(b'\n')));
+        builder
+    }
+}
+
+/// Parse CLI args: positional `root_dir out_dir [delim]` plus
+/// `--header-mode <first|union>`, `--jobs N`, `--trim`, `--flexible`,
+/// `--quote`, `--terminator`, `--dedup`, `--dedup-key`, `--recursive`,
+/// `--include-ext`, `--exclude-ext`, `--exclude-dir`, `--compress` and
+/// `--manifest`.
+pub fn parse_args(args: &[String]) -> Result<Config> {
+    let mut root_dir = None;
+    let mut out_dir = None;
+    let mut delim_char = None;
+    let mut header_mode = HeaderMode::First;
+    let mut jobs = None;
+    let mut trim = Trim::None;
+    let mut flexible = false;
+    let mut quote = b'"';
+    let mut terminator = None;
+    let mut dedup = false;
+    let mut dedup_key = None;
+    let mut recursive = false;
+    let mut include_ext = None;
+    let mut exclude_ext = Vec::new();
+    let mut exclude_dir = Vec::new();
+    let mut compress = false;
+    let mut manifest = false;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--header-mode" => {
+                let val = iter
+                    .next()
+                    .context("--header-mode requires a value ('first' or 'union')")?;
+                header_mode = HeaderMode::parse(val)?;
+            }
+            "--jobs" => {
+                let val = iter.next().context("--jobs requires a value")?;
+                let n: usize = val
+                    .parse()
+                    .with_context(|| format!("invalid --jobs value '{}'", val))?;
+                if n == 0 {
+                    bail!("--jobs must be at least 1");
+                }
+                jobs = Some(n);
+            }
+            "--trim" => {
+                let val = iter
+                    .next()
+                    .context("--trim requires a value ('none', 'headers', 'fields' or 'all')")?;
+                trim = parse_trim(val)?;
+            }
+            "--flexible" => flexible = true,
+            "--quote" => {
+                let val = iter.next().context("--quote requires a value")?;
+                quote = parse_ascii_char("--quote", val)?;
+            }
+            "--terminator" => {
+                let val = iter.next().context("--terminator requires a value")?;
+                terminator = Some(parse_terminator(val)?);
+            }
+            "--dedup" => dedup = true,
+            "--dedup-key" => {
+                let val = iter.next().context("--dedup-key requires a value")?;
+                dedup = true;
+                dedup_key = Some(val.split(',').map(str::to_string).collect());
+            }
+            "--recursive" => recursive = true,
+            "--include-ext" => {
+                let val = iter.next().context("--include-ext requires a value")?;
+                include_ext = Some(split_ext_list(val));
+            }
+            "--exclude-ext" => {
+                let val = iter.next().context("--exclude-ext requires a value")?;
+                exclude_ext.extend(split_ext_list(val));
+            }
+            "--exclude-dir" => {
+                let val = iter.next().context("--exclude-dir requires a value")?;
+                exclude_dir.push(
+                    Pattern::new(val).with_context(|| format!("invalid --exclude-dir glob '{}'", val))?,
+                );
+            }
+            "--compress" => compress = true,
+            "--manifest" => manifest = true,
+            _ if arg.starts_with("--") => bail!("unknown flag '{}'", arg),
+            _ if root_dir.is_none() => root_dir = Some(arg.clone()),
+            _ if out_dir.is_none() => out_dir = Some(arg.clone()),
+            _ if delim_char.is_none() => delim_char = Some(arg.clone()),
+            _ => bail!("unexpected extra argument '{}'", arg),
+        }
+    }
+
+    let root_dir = root_dir.unwrap_or_else(|| ".".to_string());
+    let out_dir = out_dir.unwrap_or_else(|| "./_out".to_string());
+    let delim_char = delim_char
+        .and_then(|s| s.chars().next())
+        .unwrap_or(',');
+
+    if !delim_char.is_ascii() {
+        bail!("Delimiter must be a single ASCII character");
+    }
+
+    let jobs = match jobs {
+        Some(n) => n,
+        None => std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+    };
+
+    Ok(Config {
+        root_dir,
+        out_dir,
+        delim: delim_char as u8,
+        header_mode,
+        jobs,
+        trim,
+        flexible,
+        quote,
+        terminator,
+        dedup,
+        dedup_key,
+        recursive,
+        include_ext,
+        exclude_ext,
+        exclude_dir,
+        compress,
+        manifest,
+    })
+}
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+/// True if `path`'s extension is `.gz` (case-insensitive).
+pub fn is_gz(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(|ext| ext.eq_ignore_ascii_case("gz"))
+        .unwrap_or(false)
+}
+
+/// Open `path` for reading, transparently decompressing gzip input so
+/// `.csv.gz` (and bare `.gz`) files concatenate alongside plain CSVs.
+/// `MultiGzDecoder` is used so a file made of several concatenated gzip
+/// members (as some export tools produce) still reads to completion.
+pub fn open_source(path: &Path) -> Result<Box<dyn Read>> {
+    let file = File::open(path).with_context(|| format!("open '{}'", path.display()))?;
+    if is_gz(path) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Output sink that's either a plain file or a gzip-compressed one,
+/// selected by `--compress`.
+pub enum Sink {
+    Plain(File),
+    Gz(GzEncoder<File>),
+}
+
+impl Sink {
+    pub fn create(path: &Path, compress: bool) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("create '{}'", path.display()))?;
+        Ok(if compress {
+            Sink::Gz(GzEncoder::new(file, Compression::default()))
+        } else {
+            Sink::Plain(file)
+        })
+    }
+
+    /// Flush and, for gzip output, write the trailing gzip footer. Must be
+    /// called before the temp file is renamed into place.
+    pub fn finish(self) -> Result<()> {
+        match self {
+            Sink::Plain(mut f) => f.flush().context("flush output file"),
+            Sink::Gz(enc) => enc.finish().map(|_| ()).context("finish gzip output"),
+        }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Plain(f) => f.write(buf),
+            Sink::Gz(g) => g.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Plain(f) => f.flush(),
+            Sink::Gz(g) => g.flush(),
+        }
+    }
+}
+
+/// Output file name for a directory, honoring `--compress`.
+pub fn output_name(dir_name: &str, compress: bool) -> String {
+    if compress {
+        format!("{}.csv.gz", dir_name)
+    } else {
+        format!("{}.csv", dir_name)
+    }
+}
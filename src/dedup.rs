@@ -0,0 +1,33 @@
+use siphasher::sip128::{Hasher128, SipHasher13};
+use std::collections::HashSet;
+use std::hash::Hasher;
+
+/// Tracks the 128-bit digests of rows already written for one output file,
+/// so each distinct row costs a fixed 16-byte digest (plus set overhead)
+/// no matter how wide the row is.
+#[derive(Default)]
+pub struct Dedup {
+    seen: HashSet<u128>,
+}
+
+impl Dedup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hash `fields` with SipHash-1-3 and return `true` if this is the first
+    /// time the digest has been seen (i.e. the row should be written).
+    pub fn insert(&mut self, fields: &[&str]) -> bool {
+        self.seen.insert(hash_fields(fields))
+    }
+}
+
+fn hash_fields(fields: &[&str]) -> u128 {
+    let mut hasher = SipHasher13::new();
+    for field in fields {
+        hasher.write(field.as_bytes());
+        // Separator so ["ab", "c"] and ["a", "bc"] don't collide.
+        hasher.write_u8(0);
+    }
+    hasher.finish128().as_u128()
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::Config;
+use crate::compress;
+
+/// True if `p`'s extension is one this run should treat as CSV input.
+/// Honors `--include-ext` / `--exclude-ext`; with neither set this is
+/// equivalent to the original literal ".csv" check. A `.gz` suffix is
+/// transparent: the extension underneath it is what's actually checked,
+/// and a bare `.gz` (no inner extension) is treated as a compressed CSV.
+pub fn is_allowed_file(p: &Path, config: &Config) -> bool {
+    let raw_ext = match p.extension().and_then(OsStr::to_str) {
+        Some(e) => e.to_ascii_lowercase(),
+        None => return false,
+    };
+
+    let ext = if compress::is_gz(p) {
+        match p
+            .file_stem()
+            .map(Path::new)
+            .and_then(|stem| stem.extension())
+            .and_then(OsStr::to_str)
+        {
+            Some(inner) => inner.to_ascii_lowercase(),
+            None => "csv".to_string(),
+        }
+    } else {
+        raw_ext
+    };
+
+    let allowed = match &config.include_ext {
+        Some(include) => include.contains(&ext),
+        None => ext == "csv",
+    };
+    allowed && !config.exclude_ext.contains(&ext)
+}
+
+/// True if a directory named `dir_name` matches one of the `--exclude-dir`
+/// glob patterns and should be skipped (and not descended into).
+pub fn is_excluded_dir(dir_name: &str, config: &Config) -> bool {
+    config.exclude_dir.iter().any(|pat| pat.matches(dir_name))
+}
+
+/// Collect the CSV-like files under `dir`. Immediate children only, unless
+/// `--recursive` is set, in which case the whole subtree is walked, skipping
+/// (and not descending into) any directory matched by `--exclude-dir`.
+pub fn collect_files(dir: &Path, config: &Config) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_files_into(dir, config, &mut files)?;
+    Ok(files)
+}
+
+fn collect_files_into(dir: &Path, config: &Config, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("reading '{}'", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if !config.recursive {
+                continue;
+            }
+            let name = path.file_name().and_then(OsStr::to_str).unwrap_or("");
+            if is_excluded_dir(name, config) {
+                continue;
+            }
+            collect_files_into(&path, config, files)?;
+        } else if path.is_file() && is_allowed_file(&path, config) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
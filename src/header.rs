@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use csv::StringRecord;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::cli::Config;
+use crate::compress;
+
+/// Read the header (first row) of a CSV. Returns (header, count).
+pub fn read_header(path: &Path, config: &Config) -> Result<(StringRecord, usize)> {
+    let mut rdr = config
+        .reader_builder()
+        .from_reader(compress::open_source(path)?);
+
+    let mut hdr = rdr.headers()?.clone();
+    strip_bom(&mut hdr);
+    let count = hdr.len();
+    Ok((hdr, count))
+}
+
+/// Compute the ordered union of every file's header in `csvs` (first-seen order).
+pub fn union_header(csvs: &[impl AsRef<Path>], config: &Config) -> Result<StringRecord> {
+    let mut seen = HashSet::new();
+    let mut union = StringRecord::new();
+
+    for path in csvs {
+        let path = path.as_ref();
+        let (hdr, _) = read_header(path, config)
+            .with_context(|| format!("read header '{}'", path.display()))?;
+        for field in hdr.iter() {
+            if seen.insert(field.to_string()) {
+                union.push_field(field);
+            }
+        }
+    }
+
+    Ok(union)
+}
+
+/// Remove UTF-8 BOM if present in the first header cell.
+pub fn strip_bom(hdr: &mut StringRecord) {
+    if let Some(first) = hdr.get(0) {
+        const BOM: &str = "\u{feff}";
+        if let Some(stripped) = first.strip_prefix(BOM) {
+            // Rebuild the record with the first field stripped of BOM.
+            let mut rebuilt = StringRecord::new();
+            for (i, field) in hdr.iter().enumerate() {
+                if i == 0 {
+                    rebuilt.push_field(stripped);
+                } else {
+                    rebuilt.push_field(field);
+                }
+            }
+            *hdr = rebuilt;
+        }
+    }
+}
+
+/// Build a mapping from canonical columns -> indices in the file header (or None if missing).
+pub fn build_mapping(canonical: &StringRecord, file_hdr: &StringRecord) -> Vec<Option<usize>> {
+    canonical
+        .iter()
+        .map(|name| file_hdr.iter().position(|h| h == name))
+        .collect()
+}
+
+/// Create an output row aligned to the canonical order.
+/// Missing cols become "", extra cols are ignored.
+pub fn map_record<'a>(
+    canonical: &StringRecord,
+    rec: &'a StringRecord,
+    map: &[Option<usize>],
+) -> Vec<&'a str> {
+    let mut out: Vec<&str> = Vec::with_capacity(canonical.len());
+    for m in map {
+        match m {
+            Some(src_idx) => {
+                // Defensive: if row is short (ragged), use empty
+                out.push(rec.get(*src_idx).unwrap_or(""));
+            }
+            None => {
+                // Missing column in file -> empty cell
+                out.push("");
+            }
+        }
+    }
+    // Extra columns in file that are not in canonical are ignored by design.
+    out
+}
+
+/// The set-level difference between a file's header and the canonical one.
+pub struct HeaderDiff {
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+/// Compute which canonical columns `file_hdr` is missing, and which columns
+/// it has that aren't canonical. Order is not considered here; see
+/// `describe_mismatch` for the order-only case.
+pub fn diff_header(canonical: &StringRecord, file_hdr: &StringRecord) -> HeaderDiff {
+    let canon_set: HashSet<&str> = canonical.iter().collect();
+    let file_set: HashSet<&str> = file_hdr.iter().collect();
+
+    let mut missing: Vec<&str> = canon_set.difference(&file_set).copied().collect();
+    let mut extra: Vec<&str> = file_set.difference(&canon_set).copied().collect();
+    missing.sort_unstable();
+    extra.sort_unstable();
+
+    HeaderDiff {
+        missing: missing.into_iter().map(String::from).collect(),
+        extra: extra.into_iter().map(String::from).collect(),
+    }
+}
+
+/// Format the warning/info lines for a file whose header doesn't match
+/// canonical exactly (`order_matches` is `canonical == file_hdr`), so
+/// callers can buffer them instead of writing to stderr directly.
+pub fn describe_mismatch(file: &Path, order_matches: bool, diff: &HeaderDiff) -> Vec<String> {
+    if order_matches {
+        return Vec::new();
+    }
+
+    if !diff.missing.is_empty() || !diff.extra.is_empty() {
+        vec![format!(
+            "WARNING: Header mismatch in '{}'. Missing: [{}] | Extra: [{}]. Columns will be reordered; missing -> empty; extra -> ignored.",
+            file.display(),
+            diff.missing.join(", "),
+            diff.extra.join(", ")
+        )]
+    } else {
+        // Same set, different order
+        vec![format!(
+            "INFO: Column order differs in '{}'. Reordering to canonical.",
+            file.display()
+        )]
+    }
+}
@@ -0,0 +1,36 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// One source file's contribution to a directory's merged output.
+#[derive(Serialize)]
+pub struct FileEntry {
+    pub path: String,
+    pub rows_written: u64,
+    pub header_matches: bool,
+    pub missing_columns: Vec<String>,
+    pub extra_columns: Vec<String>,
+}
+
+/// Auditable summary of one directory's merge, written next to its output
+/// when `--manifest` is set.
+#[derive(Serialize)]
+pub struct Manifest {
+    pub directory: String,
+    pub output: String,
+    pub canonical_columns: Vec<String>,
+    pub files: Vec<FileEntry>,
+    pub total_rows_written: u64,
+}
+
+impl Manifest {
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("serialize manifest")?;
+        let mut f = File::create(path).with_context(|| format!("create '{}'", path.display()))?;
+        f.write_all(json.as_bytes())?;
+        f.write_all(b"\n")?;
+        Ok(())
+    }
+}
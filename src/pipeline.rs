@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{Config, HeaderMode};
+use crate::compress::{self, Sink};
+use crate::dedup::Dedup;
+use crate::discover;
+use crate::header::{build_mapping, describe_mismatch, diff_header, map_record, read_header, strip_bom, union_header};
+use crate::manifest::{FileEntry, Manifest};
+
+/// One line of output, tagged with the stream it belongs on.
+pub enum Line {
+    Out(String),
+    Err(String),
+}
+
+/// Buffered output plus the outcome of processing one subdirectory.
+///
+/// Directories are processed concurrently across the worker pool, so
+/// messages are buffered here rather than printed immediately; the caller
+/// flushes each report in directory order once all workers finish, keeping
+/// output deterministic regardless of which worker finished first.
+pub struct DirReport {
+    pub lines: Vec<Line>,
+    pub outcome: Result<()>,
+}
+
+impl DirReport {
+    pub fn flush(&self) {
+        for line in &self.lines {
+            match line {
+                Line::Out(s) => println!("{}", s),
+                Line::Err(s) => eprintln!("{}", s),
+            }
+        }
+    }
+}
+
+/// Read, normalize and concatenate one subdirectory's CSVs into `out/{dir_name}.csv`
+/// (`out/{dir_name}.csv.gz` with `--compress`).
+pub fn process_dir(dir: &Path, out: &Path, config: &Config) -> DirReport {
+    let mut lines = Vec::new();
+    let outcome = process_dir_inner(dir, out, config, &mut lines);
+    DirReport { lines, outcome }
+}
+
+fn process_dir_inner(dir: &Path, out: &Path, config: &Config, lines: &mut Vec<Line>) -> Result<()> {
+    let dir_name = dir.file_name().and_then(OsStr::to_str).unwrap_or("unknown");
+
+    let mut csvs: Vec<PathBuf> = discover::collect_files(dir, config)
+        .with_context(|| format!("discovering files under '{}'", dir.display()))?;
+
+    if csvs.is_empty() {
+        lines.push(Line::Out(format!("Skipping '{}': no CSV files", dir_name)));
+        return Ok(());
+    }
+
+    // Deterministic order by path
+    csvs.sort();
+
+    let out_path = out.join(compress::output_name(dir_name, config.compress));
+    let tmp_path = PathBuf::from(format!("{}.tmp", out_path.display()));
+
+    let sink = Sink::create(&tmp_path, config.compress)?;
+    let mut wtr = config.writer_builder().from_writer(sink);
+
+    // Determine canonical header, per the configured header mode
+    let canonical = match config.header_mode {
+        HeaderMode::First => {
+            let (hdr, _count) = read_header(&csvs[0], config)
+                .with_context(|| format!("read header '{}'", csvs[0].display()))?;
+            hdr
+        }
+        HeaderMode::Union => union_header(&csvs, config)
+            .with_context(|| format!("compute union header for '{}'", dir.display()))?,
+    };
+
+    if canonical.is_empty() {
+        lines.push(Line::Err(format!(
+            "WARNING: Empty header in '{}'; skipping directory '{}'",
+            csvs[0].display(),
+            dir_name
+        )));
+        return Ok(());
+    }
+
+    // Write canonical header once
+    wtr.write_record(&canonical)?;
+
+    // Resolve --dedup-key column names to canonical indices up front; an
+    // unqualified --dedup hashes the whole canonical-ordered row.
+    let dedup_indices: Option<Vec<usize>> = match &config.dedup_key {
+        Some(keys) => Some(
+            keys.iter()
+                .map(|k| {
+                    canonical
+                        .iter()
+                        .position(|c| c == k)
+                        .with_context(|| format!("--dedup-key column '{}' not found in canonical header", k))
+                })
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        None => None,
+    };
+    let mut dedup = config.dedup.then(Dedup::new);
+    let mut dedup_dropped: u64 = 0;
+    let mut file_entries: Vec<FileEntry> = Vec::new();
+
+    // Concatenate files
+    for file in &csvs {
+        let mut rdr = config
+            .reader_builder()
+            .from_reader(compress::open_source(file)?);
+
+        // Original header (strip BOM)
+        let mut hdr = rdr.headers()?.clone();
+        strip_bom(&mut hdr);
+
+        let map = build_mapping(&canonical, &hdr);
+        let order_matches = canonical == hdr;
+        let diff = diff_header(&canonical, &hdr);
+        lines.extend(
+            describe_mismatch(file, order_matches, &diff)
+                .into_iter()
+                .map(Line::Err),
+        );
+
+        let mut rows_written: u64 = 0;
+        for result in rdr.records() {
+            let rec = result.with_context(|| format!("read row in '{}'", file.display()))?;
+            let out_row = map_record(&canonical, &rec, &map);
+            if let Some(dedup) = dedup.as_mut() {
+                let key_fields: Vec<&str> = match &dedup_indices {
+                    Some(idxs) => idxs.iter().map(|&i| out_row[i]).collect(),
+                    None => out_row.clone(),
+                };
+                if !dedup.insert(&key_fields) {
+                    dedup_dropped += 1;
+                    continue;
+                }
+            }
+            wtr.write_record(out_row)?;
+            rows_written += 1;
+        }
+
+        if config.manifest {
+            file_entries.push(FileEntry {
+                path: file.display().to_string(),
+                rows_written,
+                header_matches: order_matches,
+                missing_columns: diff.missing,
+                extra_columns: diff.extra,
+            });
+        }
+    }
+
+    wtr.flush()?;
+    let sink = wtr
+        .into_inner()
+        .map_err(|e| anyhow::anyhow!("flush csv writer for '{}': {}", tmp_path.display(), e))?;
+    sink.finish()?;
+    // Replace atomically
+    fs::rename(&tmp_path, &out_path)
+        .with_context(|| format!("move '{}' -> '{}'", tmp_path.display(), out_path.display()))?;
+
+    if dedup_dropped > 0 {
+        lines.push(Line::Err(format!(
+            "'{}': dropped {} duplicate rows",
+            dir_name, dedup_dropped
+        )));
+    }
+    lines.push(Line::Out(format!("Wrote: {}", out_path.display())));
+
+    if config.manifest {
+        let total_rows_written = file_entries.iter().map(|f| f.rows_written).sum();
+        let manifest = Manifest {
+            directory: dir.display().to_string(),
+            output: out_path.display().to_string(),
+            canonical_columns: canonical.iter().map(String::from).collect(),
+            files: file_entries,
+            total_rows_written,
+        };
+        let manifest_path = out.join(format!("{}.manifest.json", dir_name));
+        manifest
+            .write_to(&manifest_path)
+            .with_context(|| format!("write manifest '{}'", manifest_path.display()))?;
+        lines.push(Line::Out(format!("Wrote manifest: {}", manifest_path.display())));
+    }
+
+    Ok(())
+}